@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::secretbox;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+use super::AIDungeonError;
+
+const CONFIG_DIR_NAME: &str = "aidungeon2-cli";
+const SESSION_FILE_NAME: &str = "session.json";
+const KEY_FILE_NAME: &str = "key";
+
+/// Access token, login credentials and last known story, as used by
+/// `AIDungeon` once loaded. On disk this is kept sealed, see
+/// `EncryptedSession`.
+#[derive(Debug, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+pub(crate) struct SavedSession {
+    pub accessToken: String,
+    pub email: String,
+    pub password: String,
+    pub story_id: Option<u64>,
+}
+
+/// The part of `SavedSession` that must never touch disk unsealed.
+#[derive(Debug, Serialize, Deserialize)]
+#[allow(non_snake_case)]
+struct Secrets {
+    accessToken: String,
+    email: String,
+    password: String,
+}
+
+/// What actually gets written to the session file: `Secrets` sealed with
+/// `secretbox`, as `nonce || ciphertext`, base64-encoded.
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedSession {
+    sealed_secrets: String,
+    story_id: Option<u64>,
+}
+
+pub(crate) fn config_dir() -> Result<PathBuf, AIDungeonError> {
+    let mut dir = dirs::config_dir().ok_or_else(|| {
+        AIDungeonError::UnexpectedError(String::from(
+            "Could not determine user's config directory",
+        ))
+    })?;
+    dir.push(CONFIG_DIR_NAME);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn session_file_path() -> Result<PathBuf, AIDungeonError> {
+    let mut path = config_dir()?;
+    path.push(SESSION_FILE_NAME);
+    Ok(path)
+}
+
+fn key_file_path() -> Result<PathBuf, AIDungeonError> {
+    let mut path = config_dir()?;
+    path.push(KEY_FILE_NAME);
+    Ok(path)
+}
+
+/// Load the symmetric key used to seal the session, generating and
+/// persisting a fresh one (with `0600` perms) the first time around.
+fn load_or_create_key() -> Result<secretbox::Key, AIDungeonError> {
+    let path = key_file_path()?;
+
+    if let Ok(bytes) = fs::read(&path) {
+        return secretbox::Key::from_slice(&bytes)
+            .ok_or_else(|| AIDungeonError::UnexpectedError(String::from("Corrupt session key")));
+    }
+
+    let key = secretbox::gen_key();
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    file.write_all(key.as_ref())?;
+    Ok(key)
+}
+
+/// Write the session to disk, overwriting any previously saved one. The
+/// access token and credentials are sealed with `secretbox` before they
+/// touch the disk.
+pub(crate) fn save(session: &SavedSession) -> Result<(), AIDungeonError> {
+    let key = load_or_create_key()?;
+    let nonce = secretbox::gen_nonce();
+
+    let secrets = Secrets {
+        accessToken: session.accessToken.clone(),
+        email: session.email.clone(),
+        password: session.password.clone(),
+    };
+    let plaintext = serde_json::to_vec(&secrets)?;
+    let ciphertext = secretbox::seal(&plaintext, &nonce, &key);
+
+    let mut sealed = nonce.as_ref().to_vec();
+    sealed.extend_from_slice(&ciphertext);
+
+    let encrypted = EncryptedSession {
+        sealed_secrets: base64::encode(&sealed),
+        story_id: session.story_id,
+    };
+
+    let path = session_file_path()?;
+    let data = serde_json::to_string(&encrypted)?;
+    fs::write(path, data)?;
+    Ok(())
+}
+
+/// Read back a session saved by a previous run, opening the sealed
+/// secrets with the locally stored key.
+pub(crate) fn load() -> Result<SavedSession, AIDungeonError> {
+    let path = session_file_path()?;
+    let data = fs::read_to_string(path)?;
+    let encrypted: EncryptedSession = serde_json::from_str(&data)?;
+
+    let sealed =
+        base64::decode(&encrypted.sealed_secrets).map_err(|_| AIDungeonError::DecryptionFailed)?;
+    if sealed.len() < secretbox::NONCEBYTES {
+        return Err(AIDungeonError::DecryptionFailed);
+    }
+    let (nonce_bytes, ciphertext) = sealed.split_at(secretbox::NONCEBYTES);
+    let nonce =
+        secretbox::Nonce::from_slice(nonce_bytes).ok_or(AIDungeonError::DecryptionFailed)?;
+
+    let key = load_or_create_key()?;
+    let plaintext = secretbox::open(ciphertext, &nonce, &key)
+        .map_err(|_| AIDungeonError::DecryptionFailed)?;
+    let secrets: Secrets =
+        serde_json::from_slice(&plaintext).map_err(|_| AIDungeonError::DecryptionFailed)?;
+
+    Ok(SavedSession {
+        accessToken: secrets.accessToken,
+        email: secrets.email,
+        password: secrets.password,
+        story_id: encrypted.story_id,
+    })
+}
+
+/// Remove the saved session, if any. The key is left in place so a session
+/// saved again later stays compatible with any other data sealed under it.
+pub(crate) fn delete() -> Result<(), AIDungeonError> {
+    let path = session_file_path()?;
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// Payload sent to `/sessions` to start a new story.
+#[derive(Debug, Serialize)]
+#[allow(non_snake_case)]
+pub struct StartOptions<'a> {
+    pub characterType: Option<&'a str>,
+    pub customPrompt: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub storyMode: &'a str,
+}
+
+/// Payload sent to `/sessions/{id}/inputs` with the player's reply.
+#[derive(Debug, Serialize)]
+pub struct StoryTextInput<'a> {
+    pub text: &'a str,
+}
+
+/// Premade stories/modes offered by the API, as returned by
+/// `/sessions/*/config`.
+#[derive(Debug, Deserialize)]
+pub struct StartModesContainer {
+    pub modes: Vec<String>,
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// Session created by a `start_story` call.
+#[derive(Debug, Deserialize)]
+pub struct Story {
+    pub id: u64,
+    pub story: Vec<StoryText>,
+}
+
+/// Single entry of a story's text, as returned by the API.
+///
+/// `kind` is either "input" (what the player typed) or "output" (what the
+/// AI replied with). `conclusion` is only set once the story has ended,
+/// with a value of either "win" or "lose".
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StoryText {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub value: String,
+    pub conclusion: Option<String>,
+}
+
+/// One entry of `GET /sessions`: enough to let a user pick which of their
+/// existing stories to resume, without pulling down the full transcript.
+#[derive(Debug, Deserialize)]
+pub struct StorySummary {
+    pub id: u64,
+    pub title: Option<String>,
+    pub public: Option<bool>,
+}
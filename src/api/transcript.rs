@@ -0,0 +1,74 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::session;
+use super::story::StoryText;
+use super::AIDungeonError;
+
+const TRANSCRIPTS_DIR_NAME: &str = "transcripts";
+
+/// Output format for `export_story`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    PlainText,
+    Markdown,
+}
+
+fn transcript_file_path(story_id: u64) -> Result<PathBuf, AIDungeonError> {
+    let mut dir = session::config_dir()?;
+    dir.push(TRANSCRIPTS_DIR_NAME);
+    fs::create_dir_all(&dir)?;
+    dir.push(format!("{}.jsonl", story_id));
+    Ok(dir)
+}
+
+/// Append newly received story text to the local transcript for `story_id`,
+/// one JSON object per line.
+pub(crate) fn append(story_id: u64, entries: &[StoryText]) -> Result<(), AIDungeonError> {
+    let path = transcript_file_path(story_id)?;
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+
+    for entry in entries {
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    }
+
+    Ok(())
+}
+
+/// Render the local transcript for `story_id` as plain text or Markdown.
+pub(crate) fn export(story_id: u64, format: ExportFormat) -> Result<String, AIDungeonError> {
+    let path = transcript_file_path(story_id)?;
+    if !path.exists() {
+        return Err(AIDungeonError::NoTranscript);
+    }
+    let data = fs::read_to_string(path)?;
+
+    let mut output = String::new();
+    for line in data.lines() {
+        let entry: StoryText = serde_json::from_str(line)?;
+
+        match format {
+            ExportFormat::PlainText => {
+                output.push_str(&entry.value);
+                output.push('\n');
+            }
+            ExportFormat::Markdown => {
+                if entry.kind == "input" {
+                    output.push_str("> ");
+                }
+                output.push_str(&entry.value);
+                output.push_str("\n\n");
+            }
+        }
+
+        if let Some(conclusion) = &entry.conclusion {
+            match format {
+                ExportFormat::PlainText => output.push_str(&format!("[{}]\n", conclusion)),
+                ExportFormat::Markdown => output.push_str(&format!("**{}**\n\n", conclusion)),
+            }
+        }
+    }
+
+    Ok(output)
+}
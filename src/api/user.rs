@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Payload sent to the `/users` and `/users/@me` endpoints.
+///
+/// Depending on the call being made, only a subset of the fields is
+/// populated (e.g. checking whether an account exists only sends `email`).
+#[derive(Debug, Serialize)]
+#[allow(non_snake_case)]
+pub struct UserAuth<'a> {
+    pub email: Option<&'a str>,
+    pub username: Option<&'a str>,
+    pub password: Option<&'a str>,
+}
+
+/// User info as returned by the AI Dungeon API.
+#[derive(Debug, Deserialize)]
+#[allow(non_snake_case)]
+pub struct User {
+    pub id: Option<u64>,
+    pub accessToken: String,
+}
+
+/// Error body returned by the API on a non-2xx response, e.g.
+/// `{"message": "Incorrect password."}`.
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct ApiErrorBody {
+    pub message: Option<String>,
+}
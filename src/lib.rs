@@ -8,6 +8,9 @@ pub mod api {
     use story::*;
     pub mod start_options;
     use start_options::*;
+    mod session;
+    pub mod transcript;
+    use transcript::*;
 
     const URI_USERINFO: &str = "https://api.aidungeon.io/users";
     const URI_REGISTERUSER: &str = "https://api.aidungeon.io/users/@me";
@@ -24,22 +27,75 @@ pub mod api {
         /// Http client used to make requests.
         /// Already contains all necessary headers.
         http_client: reqwest::Client,
+        /// Access token currently baked into `http_client`'s headers.
+        /// Kept around separately so it can be persisted by `save_session`.
+        access_token: String,
+        /// Credentials used to transparently re-authenticate if the access
+        /// token above ever expires.
+        email: String,
+        password: String,
         story_id: Option<u64>,
+        /// Whether new story text should also be appended to a local
+        /// transcript file, see `enable_transcript_recording`.
+        record_transcript: bool,
+        /// How many entries of the current story's text have already been
+        /// appended to the local transcript, since `send_reply` re-sends
+        /// the full story on every turn rather than just the new entries.
+        transcript_recorded_len: usize,
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, thiserror::Error)]
     pub enum AIDungeonError {
+        #[error("this email is already registered")]
         EmailAlreadyExists,
+        #[error("this username is already taken")]
         UsernameAlreadyExists,
+        #[error("invalid password")]
         InvalidPassword,
-        RequestFailed(reqwest::Error),
-        InvalidResponseFromServer(serde_json::error::Error),
+        #[error("request failed: {0}")]
+        RequestFailed(#[from] reqwest::Error),
+        #[error("invalid response from server: {0}")]
+        InvalidResponseFromServer(#[from] serde_json::error::Error),
+        #[error("{0}")]
         UnexpectedError(String),
-    }
-    impl From<reqwest::Error> for AIDungeonError {
-        fn from(err: reqwest::Error) -> Self {
-            AIDungeonError::RequestFailed(err)
-        }
+        /// No saved session could be found for `from_saved_session`.
+        #[error("no saved session found")]
+        NoSavedSession,
+        /// Reading or writing a local file (session, key or transcript)
+        /// failed.
+        #[error("local file error: {0}")]
+        IoError(std::io::Error),
+        /// The saved session could not be decrypted: either the session
+        /// file was tampered with, or it was sealed with a different key.
+        #[error("could not decrypt saved session")]
+        DecryptionFailed,
+        /// The access token expired and re-authenticating with the stored
+        /// credentials also failed, so the caller needs to log in again.
+        #[error("session expired, please log in again")]
+        SessionExpired,
+        /// The API rejected the given password as incorrect for the account.
+        #[error("incorrect password")]
+        IncorrectPassword,
+        /// The account exists, but its email hasn't been verified yet.
+        #[error("account is not verified yet")]
+        UnverifiedAccount,
+        /// The verification code sent to `/users/@me` didn't match.
+        #[error("invalid verification code")]
+        InvalidVerificationCode,
+        /// Email or password was missing from the request.
+        #[error("email or password is missing")]
+        MissingCredentials,
+        /// The API refused the request outright (403 Forbidden).
+        #[error("forbidden")]
+        Forbidden,
+        /// The request body was too large for the API to accept.
+        #[error("request too large")]
+        RequestTooLarge,
+        /// `export_story` was called for a story id with no locally
+        /// recorded transcript (recording was never enabled, or nothing
+        /// was played under this id).
+        #[error("no local transcript recorded for this story")]
+        NoTranscript,
     }
     impl From<http::header::InvalidHeaderValue> for AIDungeonError {
         fn from(err: http::header::InvalidHeaderValue) -> Self {
@@ -49,9 +105,40 @@ pub mod api {
             ))
         }
     }
-    impl From<serde_json::error::Error> for AIDungeonError {
-        fn from(err: serde_json::error::Error) -> Self {
-            AIDungeonError::InvalidResponseFromServer(err)
+    impl From<std::io::Error> for AIDungeonError {
+        fn from(err: std::io::Error) -> Self {
+            if err.kind() == std::io::ErrorKind::NotFound {
+                AIDungeonError::NoSavedSession
+            } else {
+                AIDungeonError::IoError(err)
+            }
+        }
+    }
+
+    /// Turn a non-2xx response from `/users`, `/users/@me` or the `/sessions`
+    /// reads of `list_stories` and `resume_story` into a precise
+    /// `AIDungeonError`, looking at both the status code and the `message`
+    /// field of the JSON error body.
+    fn classify_auth_error(status: reqwest::StatusCode, response: &mut reqwest::Response) -> AIDungeonError {
+        let message = response
+            .json::<ApiErrorBody>()
+            .ok()
+            .and_then(|body| body.message)
+            .unwrap_or_default();
+
+        match status {
+            reqwest::StatusCode::FORBIDDEN => AIDungeonError::Forbidden,
+            reqwest::StatusCode::PAYLOAD_TOO_LARGE => AIDungeonError::RequestTooLarge,
+            reqwest::StatusCode::BAD_REQUEST if message.is_empty() => {
+                AIDungeonError::MissingCredentials
+            }
+            _ if message.contains("Incorrect password") => AIDungeonError::IncorrectPassword,
+            _ if message.contains("not verified") => AIDungeonError::UnverifiedAccount,
+            _ if message.contains("verification code") => AIDungeonError::InvalidVerificationCode,
+            _ => AIDungeonError::UnexpectedError(format!(
+                "Unexpected status code while talking to the API: {} ({})",
+                status, message
+            )),
         }
     }
 
@@ -108,11 +195,8 @@ pub mod api {
                 reqwest::StatusCode::OK => {
                     user = does_user_exist_response.json()?;
                 }
-                _ => {
-                    return Err(AIDungeonError::UnexpectedError(String::from(format!(
-                        "Bad request status code while checking whether user account exists: {}",
-                        does_user_exist_response.status()
-                    ))));
+                status => {
+                    return Err(classify_auth_error(status, &mut does_user_exist_response));
                 }
             }
 
@@ -141,7 +225,7 @@ pub mod api {
                 .build()?;
 
             // Send PATCH request with specified access token and credentials
-            let user_register_reponse = client
+            let mut user_register_reponse = client
                 .patch(URI_REGISTERUSER)
                 .json(&UserAuth {
                     username: Some(username),
@@ -155,14 +239,16 @@ pub mod api {
                     // Return prepared client with correct access token
                     Ok(AIDungeon {
                         http_client: client,
+                        access_token: user.accessToken,
+                        email: email.to_string(),
+                        password: password.to_string(),
                         story_id: None,
+                        record_transcript: false,
+                        transcript_recorded_len: 0,
                     })
                 }
                 reqwest::StatusCode::BAD_REQUEST => Err(AIDungeonError::UsernameAlreadyExists),
-                _ => Err(AIDungeonError::UnexpectedError(String::from(format!(
-                    "Bad request status code while trying to register user: {}",
-                    user_register_reponse.status()
-                )))),
+                status => Err(classify_auth_error(status, &mut user_register_reponse)),
             }
         }
 
@@ -199,11 +285,8 @@ pub mod api {
                 reqwest::StatusCode::OK => {
                     user = does_user_exist_response.json()?;
                 }
-                _ => {
-                    return Err(AIDungeonError::UnexpectedError(String::from(format!(
-                        "Bad request status code while trying to log in: {}",
-                        does_user_exist_response.status()
-                    ))));
+                status => {
+                    return Err(classify_auth_error(status, &mut does_user_exist_response));
                 }
             }
 
@@ -231,10 +314,155 @@ pub mod api {
 
             Ok(AIDungeon {
                 http_client: client,
+                access_token: user.accessToken,
+                email: email.to_string(),
+                password: password.to_string(),
                 story_id: None,
+                record_transcript: false,
+                transcript_recorded_len: 0,
             })
         }
 
+        /// Restore a session saved by a previous run via `save_session`.
+        ///
+        /// This reads the access token (and last known `story_id`) back from
+        /// the local session file and rebuilds the `x-access-token` client
+        /// without talking to `/users` at all.
+        pub fn from_saved_session() -> Result<AIDungeon, AIDungeonError> {
+            let saved = session::load()?;
+
+            let mut headers = header::HeaderMap::new();
+            headers.append(
+                header::USER_AGENT,
+                header::HeaderValue::from_static(USERAGENT),
+            );
+            headers.append(
+                "x-access-token",
+                header::HeaderValue::from_str(&saved.accessToken)?,
+            );
+
+            let client: reqwest::Client = reqwest::Client::builder()
+                .gzip(true)
+                .default_headers(headers)
+                .build()?;
+
+            Ok(AIDungeon {
+                http_client: client,
+                access_token: saved.accessToken,
+                email: saved.email,
+                password: saved.password,
+                story_id: saved.story_id,
+                record_transcript: false,
+                transcript_recorded_len: 0,
+            })
+        }
+
+        /// Start appending every `StoryText` returned by `start_story` and
+        /// `send_reply` to a local per-story JSONL transcript file.
+        pub fn enable_transcript_recording(&mut self) {
+            self.record_transcript = true;
+        }
+
+        /// Render the locally recorded transcript for `story_id` as plain
+        /// text or Markdown. Requires `enable_transcript_recording` to have
+        /// been called while that story was played.
+        pub fn export_story(&self, story_id: u64, format: ExportFormat) -> Result<String, AIDungeonError> {
+            transcript::export(story_id, format)
+        }
+
+        /// Persist the current access token, credentials and story id to
+        /// the local session file, so a later run can pick up with
+        /// `from_saved_session` instead of logging in again.
+        pub fn save_session(&self) -> Result<(), AIDungeonError> {
+            session::save(&session::SavedSession {
+                accessToken: self.access_token.clone(),
+                email: self.email.clone(),
+                password: self.password.clone(),
+                story_id: self.story_id,
+            })
+        }
+
+        /// Delete the locally saved session, if any.
+        pub fn logout() -> Result<(), AIDungeonError> {
+            session::delete()
+        }
+
+        /// Re-run the login flow with the stored credentials to obtain a
+        /// fresh access token, and rebuild `http_client` around it. The
+        /// refreshed session is immediately persisted via `save_session`.
+        ///
+        /// Called automatically by the request helpers below whenever the
+        /// API responds with `401 Unauthorized`.
+        fn reauthenticate(&mut self) -> Result<(), AIDungeonError> {
+            let mut headers = header::HeaderMap::new();
+            headers.append(
+                header::USER_AGENT,
+                header::HeaderValue::from_static(USERAGENT),
+            );
+
+            let plain_client: reqwest::Client = reqwest::Client::builder()
+                .gzip(true)
+                .default_headers(headers)
+                .build()
+                .map_err(|_| AIDungeonError::SessionExpired)?;
+
+            let mut login_response = plain_client
+                .post(URI_USERINFO)
+                .json(&UserAuth {
+                    email: Some(&self.email),
+                    password: Some(&self.password),
+                    username: None,
+                })
+                .send()
+                .map_err(|_| AIDungeonError::SessionExpired)?;
+
+            if login_response.status() != reqwest::StatusCode::OK {
+                return Err(AIDungeonError::SessionExpired);
+            }
+            let user: User = login_response
+                .json()
+                .map_err(|_| AIDungeonError::SessionExpired)?;
+
+            let mut headers = header::HeaderMap::new();
+            headers.append(
+                header::USER_AGENT,
+                header::HeaderValue::from_static(USERAGENT),
+            );
+            let access_token_header = header::HeaderValue::from_str(&user.accessToken)
+                .map_err(|_| AIDungeonError::SessionExpired)?;
+            headers.append("x-access-token", access_token_header);
+
+            let client: reqwest::Client = reqwest::Client::builder()
+                .gzip(true)
+                .default_headers(headers)
+                .build()
+                .map_err(|_| AIDungeonError::SessionExpired)?;
+
+            self.http_client = client;
+            self.access_token = user.accessToken;
+
+            // Keep the on-disk session in step with the refreshed token, so
+            // a later `from_saved_session` doesn't start out already stale.
+            self.save_session()?;
+
+            Ok(())
+        }
+
+        /// Send a request built by `make_request`, transparently
+        /// re-authenticating and replaying it exactly once if the API
+        /// responds with `401 Unauthorized`.
+        fn send_with_reauth<F>(&mut self, make_request: F) -> Result<reqwest::Response, AIDungeonError>
+        where
+            F: Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+        {
+            let response = make_request(&self.http_client).send()?;
+            if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+                self.reauthenticate()?;
+                return Ok(make_request(&self.http_client).send()?);
+            }
+            Ok(response)
+        }
+
         /// Start new story.
         ///
         /// Custom prompt should be none, unless story_mode is "custom".
@@ -251,16 +479,15 @@ pub mod api {
             name: Option<&str>,
             character_type: Option<&str>,
         ) -> Result<Vec<StoryText>, AIDungeonError> {
-            let mut user_input_reply: reqwest::Response = self
-                .http_client
-                .post(URI_NEW_SESSION)
-                .json(&StartOptions {
-                    characterType: character_type,
-                    customPrompt: custom_prompt,
-                    name: name,
-                    storyMode: story_mode,
-                })
-                .send()?;
+            let mut user_input_reply: reqwest::Response =
+                self.send_with_reauth(|client| {
+                    client.post(URI_NEW_SESSION).json(&StartOptions {
+                        characterType: character_type,
+                        customPrompt: custom_prompt,
+                        name: name,
+                        storyMode: story_mode,
+                    })
+                })?;
 
             let response: Story;
             match user_input_reply.status() {
@@ -276,6 +503,10 @@ pub mod api {
             }
 
             self.story_id = Some(response.id);
+            if self.record_transcript {
+                transcript::append(response.id, &response.story)?;
+            }
+            self.transcript_recorded_len = response.story.len();
 
             Ok(response.story)
         }
@@ -289,21 +520,17 @@ pub mod api {
         /// As text, we send user's input. We receive array of responses,
         /// each has type (input/output) and value (texti itself), and sometimes
         /// conclusion (win/lose)
-        pub fn send_reply<'a>(&self, text: &str) -> Result<Vec<StoryText>, AIDungeonError> {
+        pub fn send_reply<'a>(&mut self, text: &str) -> Result<Vec<StoryText>, AIDungeonError> {
             if self.story_id.is_none() {
                 return Err(AIDungeonError::UnexpectedError(String::from(
                     "There is no running story, but tried to send reply.",
                 )));
             }
 
-            let mut user_input_reply: reqwest::Response = self
-                .http_client
-                .post(
-                    &URI_CURRENT_SESSION
-                        .replace("[SESSIONID]", &self.story_id.unwrap().to_string()),
-                )
-                .json(&StoryTextInput { text })
-                .send()?;
+            let uri = URI_CURRENT_SESSION.replace("[SESSIONID]", &self.story_id.unwrap().to_string());
+            let mut user_input_reply: reqwest::Response = self.send_with_reauth(|client| {
+                client.post(&uri).json(&StoryTextInput { text })
+            })?;
 
             let response: Vec<StoryText>;
             match user_input_reply.status() {
@@ -318,12 +545,24 @@ pub mod api {
                 }
             }
 
+            // `response` is the full story so far, not just this turn's new
+            // entries, so only the tail past what we've already recorded is
+            // new (otherwise the transcript would grow quadratically).
+            if self.record_transcript {
+                let already_recorded = self.transcript_recorded_len.min(response.len());
+                let new_entries = &response[already_recorded..];
+                if !new_entries.is_empty() {
+                    transcript::append(self.story_id.unwrap(), new_entries)?;
+                }
+            }
+            self.transcript_recorded_len = response.len();
+
             Ok(response)
         }
 
-        pub fn get_recommended_story(&self) -> Result<StartModesContainer, AIDungeonError> {
+        pub fn get_recommended_story(&mut self) -> Result<StartModesContainer, AIDungeonError> {
             let mut ask_for_configurations_response: reqwest::Response =
-                self.http_client.get(URI_START_OPTIONS).send()?;
+                self.send_with_reauth(|client| client.get(URI_START_OPTIONS))?;
 
             let response: StartModesContainer;
             match ask_for_configurations_response.status() {
@@ -340,5 +579,40 @@ pub mod api {
 
             Ok(response)
         }
+
+        /// List the stories already saved on the user's account.
+        ///
+        /// GETs https://api.aidungeon.io/sessions, which returns one summary
+        /// per existing session. Use `resume_story` to continue one of them.
+        pub fn list_stories(&mut self) -> Result<Vec<StorySummary>, AIDungeonError> {
+            let mut list_sessions_response: reqwest::Response =
+                self.send_with_reauth(|client| client.get(URI_NEW_SESSION))?;
+
+            match list_sessions_response.status() {
+                reqwest::StatusCode::OK => Ok(list_sessions_response.json()?),
+                status => Err(classify_auth_error(status, &mut list_sessions_response)),
+            }
+        }
+
+        /// Resume a previously started story.
+        ///
+        /// GETs https://api.aidungeon.io/sessions/STORYID/inputs to fetch the
+        /// full transcript, then sets `self.story_id` so subsequent calls to
+        /// `send_reply` continue this story rather than a new one.
+        pub fn resume_story(&mut self, story_id: u64) -> Result<Vec<StoryText>, AIDungeonError> {
+            let uri = URI_CURRENT_SESSION.replace("[SESSIONID]", &story_id.to_string());
+            let mut resume_story_response: reqwest::Response =
+                self.send_with_reauth(|client| client.get(&uri))?;
+
+            let response: Vec<StoryText> = match resume_story_response.status() {
+                reqwest::StatusCode::OK => resume_story_response.json()?,
+                status => return Err(classify_auth_error(status, &mut resume_story_response)),
+            };
+
+            self.story_id = Some(story_id);
+            self.transcript_recorded_len = 0;
+
+            Ok(response)
+        }
     }
 }